@@ -0,0 +1,98 @@
+/**
+ * Copyright 2020 Google LLC
+ * Copyright 2020 Team Spacecat
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#[path = "../eval.rs"]
+mod eval;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use anyhow::Result;
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use eval::{Env, Evaluator};
+
+fn main() -> Result<()> {
+    let mut env = Env::new_std();
+    {
+        let mut code = String::new();
+        let mut f = File::open("galaxy.txt")?;
+        f.read_to_string(&mut code)?;
+        env.parse_defs(&code)?;
+    }
+
+    // The evaluator is kept across lines so users can watch the running
+    // evaluation cost accumulate.
+    let mut eval = Evaluator::new();
+    let mut rl = Editor::<()>::new()?;
+
+    'session: loop {
+        let mut prompt = "galaxy> ";
+        let mut buffer = String::new();
+        loop {
+            match rl.readline(prompt) {
+                Ok(line) => {
+                    rl.add_history_entry(line.as_str());
+                    if !buffer.is_empty() {
+                        buffer.push(' ');
+                    }
+                    buffer.push_str(line.trim());
+                }
+                // Ctrl-C abandons the current (possibly multi-line) input.
+                Err(ReadlineError::Interrupted) => break,
+                Err(ReadlineError::Eof) => break 'session,
+                Err(e) => return Err(e.into()),
+            }
+
+            let input = buffer.trim();
+            if input.is_empty() {
+                break;
+            }
+
+            // `:name = <expr>` defines a new symbol; everything else is a bare
+            // expression to evaluate.
+            if input.contains(" = ") {
+                if let Err(e) = env.parse_defs(input) {
+                    eprintln!("error: {:#}", e);
+                }
+                break;
+            }
+
+            match env.parse_expr(input) {
+                Ok(expr) => {
+                    match eval.to_string(expr) {
+                        Ok(s) => println!("{}", s),
+                        Err(e) => eprintln!("error: {:#}", e),
+                    }
+                    println!("; evals: {}", eval.count);
+                    break;
+                }
+                // An unfinished expression continues on the next line.
+                Err(e) if format!("{}", e).contains("Unexpected EOF") => {
+                    prompt = "    ... ";
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -18,9 +18,10 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use num_bigint::BigInt;
 
-pub type Num = i128;
+pub type Num = BigInt;
 
 // A partially evaluated value.
 #[derive(Clone)]
@@ -34,8 +35,8 @@ pub enum Value {
 }
 
 impl Value {
-    pub fn new_num(n: Num) -> Value {
-        Value::Num(n)
+    pub fn new_num(n: impl Into<Num>) -> Value {
+        Value::Num(n.into())
     }
 
     pub fn new_nil() -> Value {
@@ -66,7 +67,7 @@ impl Value {
 
     pub fn as_num(&self) -> Result<Num> {
         if let Value::Num(n) = self {
-            return Ok(*n);
+            return Ok(n.clone());
         }
         bail!("not a number");
     }
@@ -111,6 +112,66 @@ impl Value {
     }
 }
 
+// A source location, tracked through tokenization so that parse and runtime
+// errors can point at the offending token.
+#[derive(Clone, Copy, Debug)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {} column {}", self.line, self.column)
+    }
+}
+
+// A whitespace-delimited token together with where it started.
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    pos: Position,
+}
+
+// Splits code into tokens on ASCII whitespace, tracking the line/column of each
+// token so that diagnostics can point back into the source.
+fn tokenize(code: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut column = 1;
+    let mut start: Option<Position> = None;
+    for (offset, ch) in code.char_indices() {
+        if ch.is_ascii_whitespace() {
+            if let Some(pos) = start.take() {
+                tokens.push(Token {
+                    text: &code[pos.offset..offset],
+                    pos,
+                });
+            }
+        } else if start.is_none() {
+            start = Some(Position {
+                offset,
+                line,
+                column,
+            });
+        }
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    if let Some(pos) = start {
+        tokens.push(Token {
+            text: &code[pos.offset..],
+            pos,
+        });
+    }
+    tokens
+}
+
 enum ExprData {
     // A partially evaluated value.
     Value(Value),
@@ -123,21 +184,32 @@ enum ExprData {
 #[derive(Clone)]
 pub struct Expr {
     data: Rc<RefCell<ExprData>>,
+    // Source location this expression was parsed from, if any. Used to decorate
+    // errors raised while forcing the expression.
+    pos: Option<Position>,
 }
 
 impl Expr {
     pub fn new_value(v: Value) -> Expr {
         Expr {
             data: Rc::new(RefCell::new(ExprData::Value(v))),
+            pos: None,
         }
     }
 
     pub fn new_thunk(f: impl Fn(&mut Evaluator) -> Result<Value> + 'static) -> Expr {
         Expr {
             data: Rc::new(RefCell::new(ExprData::Thunk(Rc::new(f)))),
+            pos: None,
         }
     }
 
+    // Tags this expression with a source location for diagnostics.
+    fn with_pos(mut self, pos: Position) -> Expr {
+        self.pos = Some(pos);
+        self
+    }
+
     pub fn apply(&self, arg: Expr) -> Result<Expr> {
         // Optimization: if self is a value, apply immediately.
         let data = self.data.borrow();
@@ -185,33 +257,35 @@ impl Expr {
     }
 
     fn parse(env: &Env, code: &str) -> Result<Expr> {
-        let (expr, mut iter) = Expr::parse_iter(env, code.split_ascii_whitespace())?;
+        let (expr, mut iter) = Expr::parse_iter(env, tokenize(code).into_iter())?;
         if let Some(token) = iter.next() {
-            bail!("Excessive token {}", token);
+            bail!("Excessive token {} at {}", token.text, token.pos);
         }
         Ok(expr)
     }
 
-    fn parse_iter<'a, T: Iterator<Item = &'a str>>(env: &Env, mut iter: T) -> Result<(Expr, T)> {
-        let name: String = iter.next().ok_or_else(|| anyhow!("Unexpected EOF"))?.into();
-        if name == "ap" {
+    fn parse_iter<'a, T: Iterator<Item = Token<'a>>>(env: &Env, mut iter: T) -> Result<(Expr, T)> {
+        let token = iter.next().ok_or_else(|| anyhow!("Unexpected EOF"))?;
+        let pos = token.pos;
+        if token.text == "ap" {
             let (lhs, iter) = Expr::parse_iter(env, iter)?;
             let (rhs, iter) = Expr::parse_iter(env, iter)?;
-            Ok((lhs.apply(rhs)?, iter))
-        } else if let Ok(n) = name.parse() {
-            Ok((Value::new_num(n).into(), iter))
-        } else if let Some(expr) = env.lookup(&name) {
+            Ok((lhs.apply(rhs)?.with_pos(pos), iter))
+        } else if let Ok(n) = token.text.parse::<Num>() {
+            Ok((Expr::from(Value::new_num(n)).with_pos(pos), iter))
+        } else if let Some(expr) = env.lookup(token.text) {
             // Optimization: if name is already defined, resolve it immediately.
-            Ok((expr, iter))
+            Ok((expr.with_pos(pos), iter))
         } else {
             let env = env.clone();
+            let name = token.text.to_string();
             let expr = Expr::new_thunk(move |eval| {
                 eval.to_value(
                     env.lookup(&name)
-                        .ok_or_else(|| anyhow!("Undefined symbol {}", &name))?,
+                        .ok_or_else(|| anyhow!("Undefined symbol {} at {}", &name, pos))?,
                 )
             });
-            Ok((expr, iter))
+            Ok((expr.with_pos(pos), iter))
         }
     }
 }
@@ -222,24 +296,128 @@ impl From<Value> for Expr {
     }
 }
 
+// A structured evaluation failure raised when an Evaluator's configured limits
+// are exceeded, instead of overrunning the native stack or looping forever.
+#[derive(Debug)]
+pub enum EvalError {
+    BudgetExceeded { count: i64, trace: Vec<Position> },
+    DepthExceeded { depth: usize, trace: Vec<Position> },
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EvalError::BudgetExceeded { count, trace } => write!(
+                f,
+                "evaluation budget exceeded after {} steps{}",
+                count,
+                format_trace(trace)
+            ),
+            EvalError::DepthExceeded { depth, trace } => write!(
+                f,
+                "evaluation depth exceeded at depth {}{}",
+                depth,
+                format_trace(trace)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+fn format_trace(trace: &[Position]) -> String {
+    if trace.is_empty() {
+        return String::new();
+    }
+    let frames: Vec<String> = trace.iter().map(|p| p.to_string()).collect();
+    format!(" (near {})", frames.join(" <- "))
+}
+
 // Provides the only way to evaluate thunks to values.
 #[derive(Debug)]
 pub struct Evaluator {
     // Number of thunks evaluated to values so far.
     pub count: i64,
+    // Optional step budget and recursion depth limits.
+    max_steps: Option<i64>,
+    max_depth: Option<usize>,
+    // Current forcing depth and a stack of the locations being forced.
+    depth: usize,
+    trace: Vec<Position>,
 }
 
 impl Evaluator {
     pub fn new() -> Evaluator {
-        Evaluator { count: 0 }
+        Evaluator {
+            count: 0,
+            max_steps: None,
+            max_depth: None,
+            depth: 0,
+            trace: Vec::new(),
+        }
+    }
+
+    // Creates an Evaluator that aborts with an EvalError instead of forcing more
+    // than max_steps thunks (finished ones plus those still being forced) or
+    // letting the forcing depth exceed max_depth.
+    pub fn with_limits(max_steps: Option<i64>, max_depth: Option<usize>) -> Evaluator {
+        Evaluator {
+            max_steps,
+            max_depth,
+            ..Evaluator::new()
+        }
+    }
+
+    // Returns a small window of the current thunk chain for diagnostics.
+    fn backtrace(&self) -> Vec<Position> {
+        let start = self.trace.len().saturating_sub(8);
+        self.trace[start..].to_vec()
     }
 
     pub fn to_value(&mut self, expr: Expr) -> Result<Value> {
+        let pos = expr.pos;
         let mut data = expr.data.borrow_mut();
         Ok(match *data {
             ExprData::Value(ref v) => v.clone(),
             ExprData::Thunk(ref f) => {
-                let v = f(self)?;
+                if let Some(max) = self.max_steps {
+                    // Thunks still being forced count toward the budget too, so
+                    // that a chain that never finishes a thunk is still stopped.
+                    let steps = self.count + self.depth as i64;
+                    if steps >= max {
+                        return Err(EvalError::BudgetExceeded {
+                            count: steps,
+                            trace: self.backtrace(),
+                        }
+                        .into());
+                    }
+                }
+                self.depth += 1;
+                if let Some(max) = self.max_depth {
+                    if self.depth > max {
+                        let depth = self.depth;
+                        self.depth -= 1;
+                        return Err(EvalError::DepthExceeded {
+                            depth,
+                            trace: self.backtrace(),
+                        }
+                        .into());
+                    }
+                }
+                if let Some(pos) = pos {
+                    self.trace.push(pos);
+                }
+                let forced = f(self);
+                if pos.is_some() {
+                    self.trace.pop();
+                }
+                self.depth -= 1;
+                // Decorate the error with this frame's location as it unwinds,
+                // building up a backtrace of the forced thunk chain.
+                let v = forced.map_err(|e| match pos {
+                    Some(pos) => e.context(format!("while forcing expression at {}", pos)),
+                    None => e,
+                })?;
                 self.count += 1;
                 *data = ExprData::Value(v.clone());
                 v
@@ -315,6 +493,140 @@ impl Modulatable {
             Modulatable::Cons(car, cdr) => format!("({} . {})", car.to_string(), cdr.to_string()),
         }
     }
+
+    // Encodes this value into the ICFPC bit-signal format.
+    pub fn modulate(&self) -> String {
+        let mut out = String::new();
+        self.modulate_into(&mut out);
+        out
+    }
+
+    fn modulate_into(&self, out: &mut String) {
+        match self {
+            Modulatable::List(elems) => {
+                for elem in elems {
+                    out.push_str("11");
+                    elem.modulate_into(out);
+                }
+                // A list is a cons chain terminated by Nil.
+                out.push_str("00");
+            }
+            Modulatable::Cons(car, cdr) => {
+                out.push_str("11");
+                car.modulate_into(out);
+                cdr.modulate_into(out);
+            }
+            Modulatable::Num(n) => {
+                let (sign_bits, mag) = if n.sign() == num_bigint::Sign::Minus {
+                    ("10", -n)
+                } else {
+                    ("01", n.clone())
+                };
+                out.push_str(sign_bits);
+                // Minimum number of 4-bit nibbles holding the magnitude (0 for zero).
+                let k = ((mag.bits() + 3) / 4) as usize;
+                for _ in 0..k {
+                    out.push('1');
+                }
+                out.push('0');
+                for i in (0..4 * k).rev() {
+                    out.push(if mag.bit(i as u64) { '1' } else { '0' });
+                }
+            }
+        }
+    }
+
+    // Materializes this value back into an Expr tree.
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            Modulatable::Num(n) => Value::new_num(n.clone()).into(),
+            Modulatable::Cons(car, cdr) => Value::new_cons(car.to_expr(), cdr.to_expr()).into(),
+            Modulatable::List(elems) => elems
+                .iter()
+                .rev()
+                .fold(Value::new_nil().into(), |cdr, car| {
+                    Value::new_cons(car.to_expr(), cdr).into()
+                }),
+        }
+    }
+}
+
+// Serializes a value to the ICFPC bit-signal format.
+pub fn modulate_string(m: &Modulatable) -> String {
+    m.modulate()
+}
+
+// Parses a value out of the ICFPC bit-signal format.
+pub fn demodulate_string(bits: &str) -> Result<Modulatable> {
+    let bits = bits.as_bytes();
+    let mut pos = 0;
+    let m = demodulate_at(bits, &mut pos)?;
+    if pos != bits.len() {
+        bail!("Excessive bits after demodulation");
+    }
+    Ok(m)
+}
+
+fn demodulate_bit(bits: &[u8], pos: &mut usize) -> Result<u8> {
+    let b = *bits
+        .get(*pos)
+        .ok_or_else(|| anyhow!("Unexpected end of signal"))?;
+    *pos += 1;
+    Ok(b)
+}
+
+fn demodulate_at(bits: &[u8], pos: &mut usize) -> Result<Modulatable> {
+    let b0 = demodulate_bit(bits, pos)?;
+    let b1 = demodulate_bit(bits, pos)?;
+    match (b0, b1) {
+        (b'0', b'0') => Ok(Modulatable::List(vec![])),
+        (b'1', b'1') => {
+            let car = demodulate_at(bits, pos)?;
+            let cdr = demodulate_at(bits, pos)?;
+            // Collapse cons-onto-list back into a List, mirroring to_modulatable.
+            Ok(match cdr {
+                Modulatable::List(mut elems) => {
+                    elems.insert(0, Box::new(car));
+                    Modulatable::List(elems)
+                }
+                other => Modulatable::Cons(Box::new(car), Box::new(other)),
+            })
+        }
+        (s0, s1) => {
+            let neg = match (s0, s1) {
+                (b'0', b'1') => false,
+                (b'1', b'0') => true,
+                _ => bail!("Invalid signal prefix"),
+            };
+            let mut k = 0usize;
+            loop {
+                match demodulate_bit(bits, pos)? {
+                    b'1' => k += 1,
+                    b'0' => break,
+                    _ => bail!("Invalid unary length"),
+                }
+            }
+            let mut mag = BigInt::from(0);
+            for _ in 0..4 * k {
+                mag <<= 1;
+                match demodulate_bit(bits, pos)? {
+                    b'1' => mag += 1,
+                    b'0' => {}
+                    _ => bail!("Invalid magnitude bit"),
+                }
+            }
+            Ok(Modulatable::Num(if neg { -mag } else { mag }))
+        }
+    }
+}
+
+// Builds a bit signal (a Nil-terminated cons list of 0/1 numbers) from a bit
+// string.
+fn bits_to_signal(bits: &str) -> Expr {
+    bits.chars().rev().fold(Value::new_nil().into(), |cdr, ch| {
+        let bit = if ch == '1' { 1 } else { 0 };
+        Value::new_cons(Value::new_num(bit).into(), cdr).into()
+    })
 }
 
 struct EnvData {
@@ -363,19 +675,181 @@ impl Env {
     }
 
     pub fn parse_defs(&mut self, code: &str) -> Result<()> {
-        for line in code.lines() {
+        for (lineno, line) in code.lines().enumerate() {
             let v: Vec<&str> = line.split(" = ").collect();
             if v.len() != 2 {
-                bail!("Syntax error");
+                bail!("Syntax error at line {}", lineno + 1);
             }
-            self.define(v[0], Expr::parse(self, v[1])?)?;
+            let expr = Expr::parse(self, v[1])
+                .with_context(|| format!("in definition of {} at line {}", v[0], lineno + 1))?;
+            self.define(v[0], expr)?;
+        }
+        Ok(())
+    }
+
+    // Registers a native operator of the given arity. The closure is invoked
+    // with a slice of its forced-lazily argument expressions once all arguments
+    // have been applied; an arity of 0 registers a plain value.
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: usize,
+        f: impl Fn(&[Expr]) -> Result<Expr> + Clone + 'static,
+    ) -> Result<()> {
+        self.define(name, curry(arity, Vec::new(), f))
+    }
+
+    // Registers a unary native operator, a thin wrapper over Value::new_func.
+    pub fn register_func(
+        &mut self,
+        name: &str,
+        f: impl Fn(Expr) -> Result<Expr> + 'static,
+    ) -> Result<()> {
+        self.define(name, Value::new_func(f).into())
+    }
+
+    // Registers a binary native operator, a thin wrapper over Value::new_func2.
+    pub fn register_func2(
+        &mut self,
+        name: &str,
+        f: impl Fn(Expr, Expr) -> Result<Expr> + Clone + 'static,
+    ) -> Result<()> {
+        self.define(name, Value::new_func2(f).into())
+    }
+
+    // Registers a ternary native operator, a thin wrapper over Value::new_func3.
+    pub fn register_func3(
+        &mut self,
+        name: &str,
+        f: impl Fn(Expr, Expr, Expr) -> Result<Expr> + Clone + 'static,
+    ) -> Result<()> {
+        self.define(name, Value::new_func3(f).into())
+    }
+}
+
+// Builds a curried Value that collects `arity` arguments before invoking `f`.
+fn curry(
+    arity: usize,
+    collected: Vec<Expr>,
+    f: impl Fn(&[Expr]) -> Result<Expr> + Clone + 'static,
+) -> Expr {
+    if arity == collected.len() {
+        return f(&collected).unwrap_or_else(|_| Value::new_nil().into());
+    }
+    Value::new_func(move |arg| {
+        let mut args = collected.clone();
+        args.push(arg);
+        if args.len() == arity {
+            f(&args)
+        } else {
+            Ok(curry(arity, args, f.clone()))
+        }
+    })
+    .into()
+}
+
+// Assembles the native builtins, letting an embedder opt in or out of groups
+// and inject its own operators before building an Env.
+pub struct StdlibBuilder {
+    arithmetic: bool,
+    list_ops: bool,
+    booleans: bool,
+    combinators: bool,
+    signal: bool,
+    extra: Vec<(String, Value)>,
+}
+
+impl StdlibBuilder {
+    // A builder with every core group enabled.
+    pub fn new() -> StdlibBuilder {
+        StdlibBuilder {
+            arithmetic: true,
+            list_ops: true,
+            booleans: true,
+            combinators: true,
+            signal: true,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn arithmetic(mut self, enabled: bool) -> StdlibBuilder {
+        self.arithmetic = enabled;
+        self
+    }
+
+    pub fn list_ops(mut self, enabled: bool) -> StdlibBuilder {
+        self.list_ops = enabled;
+        self
+    }
+
+    pub fn booleans(mut self, enabled: bool) -> StdlibBuilder {
+        self.booleans = enabled;
+        self
+    }
+
+    pub fn combinators(mut self, enabled: bool) -> StdlibBuilder {
+        self.combinators = enabled;
+        self
+    }
+
+    pub fn signal(mut self, enabled: bool) -> StdlibBuilder {
+        self.signal = enabled;
+        self
+    }
+
+    // Injects a caller-provided native operator into the resulting Env.
+    pub fn with(mut self, name: &str, value: Value) -> StdlibBuilder {
+        self.extra.push((name.into(), value));
+        self
+    }
+
+    // Defines the selected groups into an existing Env.
+    pub fn define(self, env: &mut Env) -> Result<()> {
+        let mut defs: Vec<(&str, Value)> = Vec::new();
+        if self.arithmetic {
+            defs.extend(group_arithmetic());
+        }
+        if self.combinators {
+            defs.extend(group_combinators());
+        }
+        if self.booleans {
+            defs.extend(group_booleans());
+        }
+        if self.list_ops {
+            defs.extend(group_list_ops());
+        }
+        if self.signal {
+            defs.extend(group_signal());
+        }
+        for (name, value) in defs {
+            env.define(name, value.into())?;
+        }
+        for (name, value) in self.extra {
+            env.define(&name, value.into())?;
         }
         Ok(())
     }
+
+    // Builds a fresh Env from the selected groups.
+    pub fn build(self) -> Result<Env> {
+        let mut env = Env::new();
+        self.define(&mut env)?;
+        Ok(env)
+    }
+}
+
+impl Default for StdlibBuilder {
+    fn default() -> StdlibBuilder {
+        StdlibBuilder::new()
+    }
 }
 
 pub fn define_builtins(env: &mut Env) -> Result<()> {
-    let defs = vec![
+    StdlibBuilder::new().define(env)
+}
+
+fn group_arithmetic() -> Vec<(&'static str, Value)> {
+    vec![
         (
             "inc",
             Value::new_func(|a| {
@@ -451,6 +925,11 @@ pub fn define_builtins(env: &mut Env) -> Result<()> {
                 }))
             }),
         ),
+    ]
+}
+
+fn group_combinators() -> Vec<(&'static str, Value)> {
+    vec![
         (
             "s",
             Value::new_func3(|a, b, c| {
@@ -471,9 +950,16 @@ pub fn define_builtins(env: &mut Env) -> Result<()> {
             // Do not evaluate the S combinator eagerly to avoid infinite evaluation loops.
             Value::new_func3(|a, b, c| a.apply(b.apply(c.clone())?)),
         ),
-        ("t", Value::new_bool(true)),
-        ("f", Value::new_bool(false)),
         ("i", Value::new_func(|a| Ok(a))),
+    ]
+}
+
+fn group_booleans() -> Vec<(&'static str, Value)> {
+    vec![("t", Value::new_bool(true)), ("f", Value::new_bool(false))]
+}
+
+fn group_list_ops() -> Vec<(&'static str, Value)> {
+    vec![
         (
             "cons",
             Value::new_func2(|a, b| Ok(Value::new_cons(a, b).into())),
@@ -489,10 +975,34 @@ pub fn define_builtins(env: &mut Env) -> Result<()> {
                 }))
             }),
         ),
-    ];
-    defs.into_iter()
-        .map(|p| env.define(p.0, p.1.into()))
-        .collect()
+    ]
+}
+
+fn group_signal() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "mod",
+            Value::new_func(|a| {
+                Ok(Expr::new_thunk(move |eval| {
+                    let bits = modulate_string(&eval.to_modulatable(a.clone())?);
+                    eval.to_value(bits_to_signal(&bits))
+                }))
+            }),
+        ),
+        (
+            "dem",
+            Value::new_func(|a| {
+                Ok(Expr::new_thunk(move |eval| {
+                    let mut bits = String::new();
+                    for bit in eval.to_list(a.clone())? {
+                        let set = eval.to_value(bit)?.as_num()? != BigInt::from(0);
+                        bits.push(if set { '1' } else { '0' });
+                    }
+                    eval.to_value(demodulate_string(&bits)?.to_expr())
+                }))
+            }),
+        ),
+    ]
 }
 
 // Represents a 2D point.
@@ -581,6 +1091,124 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bignum() -> Result<()> {
+        let env = Env::new_std();
+        let mut eval = Evaluator::new();
+        // 2^127 exceeds i128::MAX, so this would overflow a fixed-width Num.
+        let big = BigInt::from(1) << 127;
+        assert_eq!(
+            eval.to_string(Expr::parse(
+                &env,
+                &format!("ap ap mul {} 2", big),
+            )?)?,
+            (&big * 2).to_string()
+        );
+        assert_eq!(
+            eval.to_string(Expr::parse(
+                &env,
+                &format!("ap ap add {} {}", big, big),
+            )?)?,
+            (&big + &big).to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_undefined_symbol_diagnostic() -> Result<()> {
+        let env = Env::new_std();
+        let mut eval = Evaluator::new();
+        let expr = Expr::parse(&env, "ap inc missing")?;
+        let err = eval.to_value(expr).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("Undefined symbol missing"), "{}", msg);
+        assert!(msg.contains("column"), "{}", msg);
+        Ok(())
+    }
+
+    #[test]
+    fn test_modulate_encoding() -> Result<()> {
+        assert_eq!(Modulatable::Num(BigInt::from(0)).modulate(), "010");
+        assert_eq!(Modulatable::Num(BigInt::from(1)).modulate(), "01100001");
+        assert_eq!(Modulatable::Num(BigInt::from(-1)).modulate(), "10100001");
+        assert_eq!(demodulate_string("00")?.to_string(), "[]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_modulate_roundtrip() -> Result<()> {
+        let env = Env::new_std();
+        let mut eval = Evaluator::new();
+        assert_eq!(
+            eval.to_string(Expr::parse(
+                &env,
+                "ap dem ap mod ap ap cons 1 ap ap cons 2 nil"
+            )?)?,
+            "[1, 2]"
+        );
+        assert_eq!(
+            eval.to_string(Expr::parse(&env, "ap dem ap mod ap ap cons 1 2")?)?,
+            "(1 . 2)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_budget_exceeded() -> Result<()> {
+        let env = Env::new_std();
+        let mut eval = Evaluator::with_limits(Some(1000), None);
+        let expr = Expr::parse(&env, "ap ap ap s i i ap ap s i i")?;
+        let err = eval.to_value(expr).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<EvalError>(),
+                Some(EvalError::BudgetExceeded { .. })
+            ),
+            "{:#}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_depth_exceeded() -> Result<()> {
+        let env = Env::new_std();
+        let mut eval = Evaluator::with_limits(None, Some(50));
+        let expr = Expr::parse(&env, "ap ap ap s i i ap ap s i i")?;
+        let err = eval.to_value(expr).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<EvalError>(),
+                Some(EvalError::DepthExceeded { .. })
+            ),
+            "{:#}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_custom_builtin() -> Result<()> {
+        let mut env = Env::new_std();
+        env.register("twice", 1, |args| {
+            let a = args[0].clone();
+            Ok(Expr::new_thunk(move |eval| {
+                Ok(Value::new_num(eval.to_value(a.clone())?.as_num()? * 2))
+            }))
+        })?;
+        let mut eval = Evaluator::new();
+        assert_eq!(eval.to_string(Expr::parse(&env, "ap twice 21")?)?, "42");
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_builder_groups() -> Result<()> {
+        let env = StdlibBuilder::new().signal(false).build()?;
+        assert!(env.lookup("add").is_some());
+        assert!(env.lookup("mod").is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_eager_optimization() -> Result<()> {
         let mut env = Env::new_std();
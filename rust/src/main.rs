@@ -16,12 +16,26 @@
  */
 mod eval;
 
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use image::{Rgb, RgbImage};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
-use eval::{Env, Evaluator, Point};
+use eval::{demodulate_string, modulate_string, Env, Evaluator, Expr, Point};
+
+// Colors cycled over the interaction's image layers.
+const LAYER_COLORS: [[u8; 3]; 6] = [
+    [255, 255, 255],
+    [255, 96, 96],
+    [96, 255, 96],
+    [96, 160, 255],
+    [255, 255, 96],
+    [255, 96, 255],
+];
 
 fn main() -> Result<()> {
     let mut env = Env::new_std();
@@ -32,24 +46,124 @@ fn main() -> Result<()> {
         env.parse_defs(&code)?;
     }
 
-    let main = env.parse_expr("galaxy")?;
+    let protocol = env.parse_expr("galaxy")?;
     let state = env.parse_expr(
         "ap ap cons 2 ap ap cons ap ap cons 1 ap ap cons -1 nil ap ap cons 0 ap ap cons nil nil",
     )?;
 
     let mut eval = Evaluator::new();
+    let click = Point {
+        x: BigInt::from(0),
+        y: BigInt::from(0),
+    };
+
+    let (new_state, layers) = interact(&mut eval, &protocol, state, click.into())?;
+    render_layers(&layers, "galaxy.png")?;
+
+    println!("State: {}", eval.to_string(new_state)?);
+    println!("Evals: {}", eval.count);
+
+    Ok(())
+}
 
-    for y in -100..=100 {
-        println!("y={}", y);
-        for x in -100..=100 {
-            let point = Point { x, y };
-            let result = eval.to_value(main.apply(state.clone())?.apply(point.into())?)?;
-            //result[0].force_modulatable()?;
-            //result[1].force_modulatable()?;
+// Drives the Galaxy interaction contract: apply the protocol to (state, vector),
+// decode the resulting [flag, newState, data] triple, and either return the
+// decoded frame layers (flag == 0, a click is required) or exchange data with
+// the server and keep looping (any other flag).
+fn interact(
+    eval: &mut Evaluator,
+    protocol: &Expr,
+    mut state: Expr,
+    mut vector: Expr,
+) -> Result<(Expr, Vec<Vec<Point>>)> {
+    loop {
+        let result = protocol.apply(state.clone())?.apply(vector.clone())?;
+        let triple = eval.to_list(result)?;
+        if triple.len() != 3 {
+            bail!("protocol did not return a [flag, newState, data] triple");
         }
+        let flag = eval.to_value(triple[0].clone())?.as_num()?;
+        let new_state = triple[1].clone();
+        let data = triple[2].clone();
+
+        if flag == BigInt::from(0) {
+            let layers = decode_layers(eval, data)?;
+            return Ok((new_state, layers));
+        }
+
+        // flag == 1: hand newState to the server and feed the reply back.
+        vector = send(eval, new_state.clone())?;
+        state = new_state;
     }
+}
 
-    println!("Evals: {}", eval.count);
+// Decodes the draw data as a list of layers, each a list of (x, y) points.
+fn decode_layers(eval: &mut Evaluator, data: Expr) -> Result<Vec<Vec<Point>>> {
+    let mut layers = Vec::new();
+    for layer in eval.to_list(data)? {
+        let mut points = Vec::new();
+        for point in eval.to_list(layer)? {
+            let x = eval.to_value(point.car()?)?.as_num()?;
+            let y = eval.to_value(point.cdr()?)?.as_num()?;
+            points.push(Point { x, y });
+        }
+        layers.push(points);
+    }
+    Ok(layers)
+}
+
+// Modulates the state, POSTs it to the alien proxy, and demodulates the reply.
+fn send(eval: &mut Evaluator, state: Expr) -> Result<Expr> {
+    let url = env::var("GALAXY_SERVER_URL")
+        .context("GALAXY_SERVER_URL must be set to reach the game server")?;
+    let bits = modulate_string(&eval.to_modulatable(state)?);
+    let body = reqwest::blocking::Client::new()
+        .post(&url)
+        .body(bits)
+        .send()?
+        .error_for_status()?
+        .text()?;
+    Ok(demodulate_string(&body)?.to_expr())
+}
+
+// Rasterizes the layers into a single multi-layer image, one color per layer.
+fn render_layers(layers: &[Vec<Point>], path: &str) -> Result<()> {
+    let mut bounds: Option<(BigInt, BigInt, BigInt, BigInt)> = None;
+    for point in layers.iter().flatten() {
+        bounds = Some(match bounds {
+            None => (point.x.clone(), point.y.clone(), point.x.clone(), point.y.clone()),
+            Some((min_x, min_y, max_x, max_y)) => (
+                min_x.min(point.x.clone()),
+                min_y.min(point.y.clone()),
+                max_x.max(point.x.clone()),
+                max_y.max(point.y.clone()),
+            ),
+        });
+    }
+
+    let (min_x, min_y, max_x, max_y) = match bounds {
+        Some(b) => b,
+        // Nothing to draw; emit a 1x1 placeholder so the frame still exists.
+        None => (BigInt::from(0), BigInt::from(0), BigInt::from(0), BigInt::from(0)),
+    };
 
+    let width = to_u32(&max_x - &min_x)? + 1;
+    let height = to_u32(&max_y - &min_y)? + 1;
+    let mut img = RgbImage::from_pixel(width, height, Rgb([0, 0, 0]));
+
+    for (i, layer) in layers.iter().enumerate() {
+        let color = Rgb(LAYER_COLORS[i % LAYER_COLORS.len()]);
+        for point in layer {
+            let px = to_u32(&point.x - &min_x)?;
+            let py = to_u32(&point.y - &min_y)?;
+            img.put_pixel(px, py, color);
+        }
+    }
+
+    img.save(path)?;
     Ok(())
 }
+
+fn to_u32(n: BigInt) -> Result<u32> {
+    n.to_u32().context("coordinate out of range")
+}